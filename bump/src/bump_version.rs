@@ -11,10 +11,18 @@ pub trait BumpVersion {
     fn increment_patch(&self) -> Self;
     /// Increments the prerelease version number.
     fn increment_prerelease(&self) -> Self;
+    /// Increments the prerelease version number if it is on the same channel as
+    /// `identifier`, otherwise starts the channel from 0.
+    fn increment_prerelease_with_identifier(&self, identifier: &str) -> Self;
     /// Add identifiers to version for prerelease
     fn append_prerelease_identifiers(&self, identifiers: &str) -> Self;
     /// Remove prerelease from version
     fn convert_prerelease_to_release(&self) -> Self;
+    /// Move prerelease to the next channel, or release if it is the last channel.
+    /// Returns `None` if the current prerelease is not on any of the channels.
+    fn promote_prerelease(&self, channels: &[String]) -> Option<Self>
+    where
+        Self: Sized;
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, ValueEnum)]
@@ -35,6 +43,38 @@ pub enum BumpType {
     Prerelease,
 }
 
+impl BumpType {
+    /// Bumps the version, `pre_id` is used by pre-* and prerelease types.
+    pub fn bump(&self, version: &Version, pre_id: Option<&str>) -> Version {
+        let prerelease_identifier = prerelease_identifier(pre_id);
+        match self {
+            BumpType::Major => version.increment_major(),
+            BumpType::Minor => version.increment_minor(),
+            BumpType::Patch => version.increment_patch(),
+            BumpType::PreMajor => version
+                .increment_major()
+                .append_prerelease_identifiers(&prerelease_identifier),
+            BumpType::PreMinor => version
+                .increment_minor()
+                .append_prerelease_identifiers(&prerelease_identifier),
+            BumpType::PrePatch => version
+                .increment_patch()
+                .append_prerelease_identifiers(&prerelease_identifier),
+            BumpType::Prerelease => match pre_id {
+                Some(pre_id) => version.increment_prerelease_with_identifier(pre_id),
+                None => version.increment_prerelease(),
+            },
+        }
+    }
+}
+
+/// Prerelease identifiers of a new prerelease, `-IDENTIFIER.0` or `-0`.
+pub fn prerelease_identifier(pre_id: Option<&str>) -> String {
+    pre_id
+        .map(|pre_id| format!("{pre_id}.0"))
+        .unwrap_or("0".to_string())
+}
+
 impl BumpVersion for Version {
     // taken from https://github.com/killercup/cargo-edit/blob/643e9253a84db02c52a7fa94f07d786d281362ab/src/version.rs#L38
     fn increment_major(&self) -> Self {
@@ -67,6 +107,11 @@ impl BumpVersion for Version {
     }
 
     fn increment_prerelease(&self) -> Self {
+        if self.pre.is_empty() {
+            return self
+                .increment_patch()
+                .append_prerelease_identifiers(&prerelease_identifier(None));
+        }
         let next_pre = increment_last_identifier(self.pre.as_str());
         let next_pre = semver::Prerelease::new(&next_pre).expect("pre release increment failed.");
         Self {
@@ -75,6 +120,25 @@ impl BumpVersion for Version {
         }
     }
 
+    fn increment_prerelease_with_identifier(&self, identifier: &str) -> Self {
+        if self.pre.is_empty() {
+            return self
+                .increment_patch()
+                .append_prerelease_identifiers(&prerelease_identifier(Some(identifier)));
+        }
+
+        let channel = match self.pre.as_str().rsplit_once('.') {
+            Some((channel, number)) if number.parse::<u64>().is_ok() => channel,
+            _ => self.pre.as_str(),
+        };
+
+        if channel == identifier {
+            self.increment_prerelease()
+        } else {
+            self.append_prerelease_identifiers(&prerelease_identifier(Some(identifier)))
+        }
+    }
+
     fn append_prerelease_identifiers(&self, identifiers: &str) -> Self {
         let next_pre = semver::Prerelease::new(identifiers).expect("pre release increment failed.");
         Self {
@@ -89,6 +153,18 @@ impl BumpVersion for Version {
             ..self.clone()
         }
     }
+
+    fn promote_prerelease(&self, channels: &[String]) -> Option<Self> {
+        let channel = self.pre.as_str().split('.').next()?;
+        let position = channels.iter().position(|c| c == channel)?;
+
+        match channels.get(position + 1) {
+            Some(next_channel) => {
+                Some(self.append_prerelease_identifiers(&format!("{next_channel}.0")))
+            }
+            None => Some(self.convert_prerelease_to_release()),
+        }
+    }
 }
 
 fn increment_last_identifier(release: &str) -> String {
@@ -1,9 +1,15 @@
+use anyhow::{anyhow, Context};
 use inquire::Select;
 use owo_colors::{colors::xterm, OwoColorize};
 use semver::Version;
-use std::fmt::{Display, Formatter};
+use serde::Deserialize;
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    fs, process,
+};
 
-use crate::bump_version::BumpVersion;
+use crate::{bump_version::BumpVersion, commit_log::group_by_type};
 
 struct VersionLabel {
     name: &'static str,
@@ -22,54 +28,126 @@ impl Display for VersionLabel {
     }
 }
 
-pub fn prompt_version_select(current_version: &Version, prerelease_identifier: &str) -> Version {
-    let mut options = vec![
-        VersionLabel::new("major", current_version.increment_major()),
-        VersionLabel::new("minor", current_version.increment_minor()),
-        VersionLabel::new("patch", current_version.increment_patch()),
-        VersionLabel::new(
-            "next",
-            if current_version.pre.is_empty() {
-                current_version.increment_patch()
-            } else {
-                current_version.increment_prerelease()
-            },
-        ),
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptOption {
+    Major,
+    Minor,
+    Patch,
+    /// Next prerelease or patch version
+    Next,
+    /// Remove prerelease, only shown for prerelease versions
+    Release,
+    PrePatch,
+    PreMinor,
+    PreMajor,
+    Current,
+}
+
+impl PromptOption {
+    pub const ALL: [PromptOption; 9] = [
+        PromptOption::Major,
+        PromptOption::Minor,
+        PromptOption::Patch,
+        PromptOption::Next,
+        PromptOption::Release,
+        PromptOption::PrePatch,
+        PromptOption::PreMinor,
+        PromptOption::PreMajor,
+        PromptOption::Current,
     ];
 
-    if !current_version.pre.is_empty() {
-        options.push(VersionLabel::new(
-            "release",
-            current_version.convert_prerelease_to_release(),
-        ))
+    pub fn is_prerelease(&self) -> bool {
+        matches!(
+            self,
+            PromptOption::PrePatch | PromptOption::PreMinor | PromptOption::PreMajor
+        )
+    }
+
+    fn version_label(
+        &self,
+        current_version: &Version,
+        prerelease_identifier: &str,
+    ) -> Option<VersionLabel> {
+        let label = match self {
+            PromptOption::Major => VersionLabel::new("major", current_version.increment_major()),
+            PromptOption::Minor => VersionLabel::new("minor", current_version.increment_minor()),
+            PromptOption::Patch => VersionLabel::new("patch", current_version.increment_patch()),
+            PromptOption::Next => VersionLabel::new(
+                "next",
+                if current_version.pre.is_empty() {
+                    current_version.increment_patch()
+                } else {
+                    current_version.increment_prerelease()
+                },
+            ),
+            PromptOption::Release => {
+                if current_version.pre.is_empty() {
+                    return None;
+                }
+                VersionLabel::new("release", current_version.convert_prerelease_to_release())
+            }
+            PromptOption::PrePatch => VersionLabel::new(
+                "pre-patch",
+                current_version
+                    .increment_patch()
+                    .append_prerelease_identifiers(prerelease_identifier),
+            ),
+            PromptOption::PreMinor => VersionLabel::new(
+                "pre-minor",
+                current_version
+                    .increment_minor()
+                    .append_prerelease_identifiers(prerelease_identifier),
+            ),
+            PromptOption::PreMajor => VersionLabel::new(
+                "pre-major",
+                current_version
+                    .increment_major()
+                    .append_prerelease_identifiers(prerelease_identifier),
+            ),
+            PromptOption::Current => VersionLabel::new("current", current_version.clone()),
+        };
+
+        Some(label)
+    }
+}
+
+pub fn print_commit_summary(last_tag: Option<&str>, subjects: &[String]) {
+    let since = last_tag.unwrap_or("the beginning");
+    println!(
+        "{} commits since {}",
+        subjects.len(),
+        since.fg::<xterm::Green>()
+    );
+
+    for (group, subjects) in group_by_type(subjects) {
+        println!("{}", group.bold());
+        for subject in subjects {
+            println!("  - {subject}");
+        }
     }
-    options.extend(vec![
-        VersionLabel::new(
-            "pre-patch",
-            current_version
-                .increment_patch()
-                .append_prerelease_identifiers(prerelease_identifier),
-        ),
-        VersionLabel::new(
-            "pre-minor",
-            current_version
-                .increment_minor()
-                .append_prerelease_identifiers(prerelease_identifier),
-        ),
-        VersionLabel::new(
-            "pre-major",
-            current_version
-                .increment_major()
-                .append_prerelease_identifiers(prerelease_identifier),
-        ),
-        VersionLabel::new("current", current_version.clone()),
-    ]);
+}
+
+pub fn prompt_version_select(
+    current_version: &Version,
+    prerelease_identifier: &str,
+    prompt_options: &[PromptOption],
+) -> Version {
+    let options: Vec<VersionLabel> = prompt_options
+        .iter()
+        .filter_map(|option| option.version_label(current_version, prerelease_identifier))
+        .collect();
+
+    let starting_cursor = options
+        .iter()
+        .position(|option| option.name == "next")
+        .unwrap_or(0);
 
     let answer = Select::new(
         &format!("Current version {}", current_version.fg::<xterm::Green>()),
         options,
     )
-    .with_starting_cursor(3)
+    .with_starting_cursor(starting_cursor)
     .prompt();
 
     match answer {
@@ -77,3 +155,72 @@ pub fn prompt_version_select(current_version: &Version, prerelease_identifier: &
         Err(_) => current_version.clone(),
     }
 }
+
+/// Asks which manifest holds the version of the project, `None` if the prompt is cancelled.
+pub fn prompt_version_file(candidates: &[&'static str]) -> Option<&'static str> {
+    Select::new(
+        "Multiple version files found, which one to bump?",
+        candidates.to_vec(),
+    )
+    .prompt()
+    .ok()
+}
+
+pub enum TagConflictResolution {
+    Abort,
+    ForceMove,
+    UseVersion(Version),
+}
+
+impl Display for TagConflictResolution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagConflictResolution::Abort => write!(f, "abort"),
+            TagConflictResolution::ForceMove => write!(f, "move the tag to the release commit"),
+            TagConflictResolution::UseVersion(version) => write!(f, "bump to {version} instead"),
+        }
+    }
+}
+
+pub fn prompt_tag_conflict(tag: &str, next_available_version: Version) -> TagConflictResolution {
+    let options = vec![
+        TagConflictResolution::Abort,
+        TagConflictResolution::ForceMove,
+        TagConflictResolution::UseVersion(next_available_version),
+    ];
+
+    let answer = Select::new(
+        &format!("Tag {} already exists", tag.fg::<xterm::Red>()),
+        options,
+    )
+    .prompt();
+
+    answer.unwrap_or(TagConflictResolution::Abort)
+}
+
+/// Opens the text in `$VISUAL` or `$EDITOR` like `git commit` does, returns the edited text.
+pub fn edit_in_editor(text: &str, file_name: &str) -> anyhow::Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = env::temp_dir().join(format!("{}-{file_name}", process::id()));
+    fs::write(&path, text)?;
+
+    // run through shell since editor can have arguments, like `code --wait`
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg(&editor)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("cannot open editor `{editor}`"))?;
+
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+
+    if status.success() {
+        Ok(edited?)
+    } else {
+        Err(anyhow!("editor `{editor}` exited with {status}"))
+    }
+}
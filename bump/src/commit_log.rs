@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+/// Commit subject parsed as conventional commit, like `feat(cli)!: add prompt`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    pub fn parse(subject: &str) -> Option<Self> {
+        let (header, description) = subject.split_once(": ")?;
+        let (header, breaking) = match header.strip_suffix('!') {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+        let (commit_type, scope) = match header.split_once('(') {
+            Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+            None => (header, None),
+        };
+
+        if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        Some(Self {
+            commit_type: commit_type.to_lowercase(),
+            scope: scope.map(String::from),
+            breaking,
+            description: description.trim().to_string(),
+        })
+    }
+}
+
+/// Groups commit subjects by conventional type. Types are ordered as breaking
+/// changes, features, fixes, other types, then commits not following the convention.
+pub fn group_by_type(subjects: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut groups: BTreeMap<(u8, String), Vec<String>> = BTreeMap::new();
+
+    for subject in subjects {
+        let key = match ConventionalCommit::parse(subject) {
+            Some(commit) if commit.breaking => (0, "breaking".to_string()),
+            Some(commit) if commit.commit_type == "feat" => (1, commit.commit_type),
+            Some(commit) if commit.commit_type == "fix" => (2, commit.commit_type),
+            Some(commit) => (3, commit.commit_type),
+            None => (4, "other".to_string()),
+        };
+        groups.entry(key).or_default().push(subject.clone());
+    }
+
+    groups
+        .into_iter()
+        .map(|((_, group), subjects)| (group, subjects))
+        .collect()
+}
+
+fn group_title(group: &str) -> String {
+    match group {
+        "breaking" => "Breaking Changes".to_string(),
+        "feat" => "Features".to_string(),
+        "fix" => "Bug Fixes".to_string(),
+        "other" => "Other".to_string(),
+        other_type => other_type.to_string(),
+    }
+}
+
+/// Renders a markdown changelog section of the release from commit subjects.
+pub fn render_changelog_section(title: &str, subjects: &[String]) -> String {
+    let mut section = format!("## {title}\n");
+
+    for (group, subjects) in group_by_type(subjects) {
+        section.push_str(&format!("\n### {}\n\n", group_title(&group)));
+        for subject in subjects {
+            let entry = match ConventionalCommit::parse(&subject) {
+                Some(ConventionalCommit {
+                    scope: Some(scope),
+                    description,
+                    ..
+                }) => format!("**{scope}:** {description}"),
+                Some(commit) => commit.description,
+                None => subject,
+            };
+            section.push_str(&format!("- {entry}\n"));
+        }
+    }
+
+    section
+}
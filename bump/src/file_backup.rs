@@ -0,0 +1,50 @@
+use anyhow::Context;
+use log::info;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Keeps original contents of files before they are bumped, so they can be restored
+/// when the release is aborted. Files created by the release are removed on restore.
+#[derive(Debug, Default)]
+pub struct FileBackup {
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl FileBackup {
+    pub fn save(&mut self, path: &Path) -> anyhow::Result<()> {
+        if self.files.iter().any(|(saved_path, _)| saved_path == path) {
+            return Ok(());
+        }
+
+        let contents = if path.exists() {
+            Some(
+                fs::read(path)
+                    .with_context(|| format!("cannot read {}", path.to_string_lossy()))?,
+            )
+        } else {
+            None
+        };
+        self.files.push((path.to_path_buf(), contents));
+
+        Ok(())
+    }
+
+    pub fn restore(&self) -> anyhow::Result<()> {
+        for (path, contents) in &self.files {
+            info!("restore {}", path.to_string_lossy());
+            match contents {
+                Some(contents) => fs::write(path, contents),
+                None => fs::remove_file(path),
+            }
+            .with_context(|| format!("cannot restore {}", path.to_string_lossy()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+}
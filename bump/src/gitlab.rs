@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, FixedOffset};
+use log::{debug, info};
+use serde::Deserialize;
+use std::env;
+
+use crate::bump_version::BumpType;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GitlabSettings {
+    pub url: String,
+    /// project id or path like `group/project`
+    pub project: String,
+    /// environment variable holding the access token
+    pub token_env: String,
+    pub major_label: String,
+    pub minor_label: String,
+    pub patch_label: String,
+}
+
+impl Default for GitlabSettings {
+    fn default() -> Self {
+        GitlabSettings {
+            url: "https://gitlab.com".to_string(),
+            project: String::new(),
+            token_env: "GITLAB_TOKEN".to_string(),
+            major_label: "semver::major".to_string(),
+            minor_label: "semver::minor".to_string(),
+            patch_label: "semver::patch".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    labels: Vec<String>,
+    merged_at: Option<DateTime<FixedOffset>>,
+}
+
+impl GitlabSettings {
+    fn bump_type_of(&self, merge_request: &MergeRequest) -> Option<BumpType> {
+        let has_label = |label: &str| merge_request.labels.iter().any(|l| l == label);
+        if has_label(&self.major_label) {
+            Some(BumpType::Major)
+        } else if has_label(&self.minor_label) {
+            Some(BumpType::Minor)
+        } else if has_label(&self.patch_label) {
+            Some(BumpType::Patch)
+        } else {
+            None
+        }
+    }
+
+    /// Derives bump type from labels of merge requests merged after `since`,
+    /// returns `None` if none of them is labeled.
+    pub fn bump_type_from_merge_requests(
+        &self,
+        since: Option<DateTime<FixedOffset>>,
+    ) -> anyhow::Result<Option<BumpType>> {
+        if self.project.is_empty() {
+            return Err(anyhow!("gitlab.project is required in settings"));
+        }
+
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests",
+            self.url.trim_end_matches('/'),
+            self.project.replace('/', "%2F")
+        );
+        let token = env::var(&self.token_env).ok();
+
+        let mut bump_type = None;
+        let mut page = Some("1".to_string());
+        while let Some(current_page) = page {
+            let mut request = ureq::get(&url)
+                .query("state", "merged")
+                .query("per_page", "100")
+                .query("page", &current_page);
+            if let Some(since) = since {
+                request = request.query("updated_after", &since.to_rfc3339());
+            }
+            if let Some(token) = &token {
+                request = request.set("PRIVATE-TOKEN", token);
+            }
+
+            debug!("list merge requests {url} page {current_page}");
+            let response = request
+                .call()
+                .with_context(|| format!("cannot list merge requests of {}", self.project))?;
+            page = response
+                .header("X-Next-Page")
+                .filter(|next_page| !next_page.is_empty())
+                .map(String::from);
+            let merge_requests: Vec<MergeRequest> = serde_json::from_str(&response.into_string()?)?;
+
+            // updated_after also matches merge requests merged before but updated later
+            let merged_since = merge_requests.iter().filter(|merge_request| {
+                match (since, merge_request.merged_at) {
+                    (Some(since), Some(merged_at)) => merged_at > since,
+                    _ => true,
+                }
+            });
+
+            for merge_request in merged_since {
+                if let Some(merge_request_bump_type) = self.bump_type_of(merge_request) {
+                    info!("!{} is {:?}", merge_request.iid, merge_request_bump_type);
+                    bump_type = match bump_type {
+                        Some(current) if rank(current) >= rank(merge_request_bump_type) => {
+                            Some(current)
+                        }
+                        _ => Some(merge_request_bump_type),
+                    };
+                }
+            }
+        }
+
+        Ok(bump_type)
+    }
+}
+
+fn rank(bump_type: BumpType) -> u8 {
+    match bump_type {
+        BumpType::Major => 3,
+        BumpType::Minor => 2,
+        _ => 1,
+    }
+}
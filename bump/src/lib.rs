@@ -0,0 +1,39 @@
+//! Core of the `bump` release tool, for scripts and workspace tools that drive
+//! bumps programmatically instead of running the binary.
+//!
+//! - [`settings`] loads `bump.toml` and resolves profiles and branch overrides.
+//! - [`repo`] reads and writes version files and runs git operations in a project.
+//! - [`bump_version`] computes the next version with [`bump_version::BumpVersion`].
+//!
+//! ```no_run
+//! use bump::{
+//!     bump_version::BumpType,
+//!     repo::Repo,
+//!     settings::{BumpFile, Settings},
+//! };
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let repo = Repo::new(std::env::current_dir()?)?;
+//! let settings = Settings::load(&repo.directory)?;
+//! let version_file = settings
+//!     .version_file
+//!     .unwrap_or_else(|| BumpFile::new("package.json"));
+//! let version = repo.read_version(&version_file)?;
+//! let next_version = BumpType::Minor.bump(&version, None).to_string();
+//!
+//! repo.bump_file(&version_file, &next_version)?;
+//! repo.stage_file(&version_file.path)?;
+//! repo.commit_changes(&next_version, false)?;
+//! repo.tag_release(&next_version, &settings.tag_prefix, settings.tag_style, false)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod bump_version;
+pub mod cli;
+pub mod commit_log;
+pub mod file_backup;
+pub mod gitlab;
+pub mod registry;
+pub mod repo;
+pub mod settings;
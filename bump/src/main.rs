@@ -1,33 +1,39 @@
-use crate::{repo::Repo, settings::Settings};
-use anyhow::bail;
-use bump_version::{BumpType, BumpVersion};
+use anyhow::{anyhow, bail, Context};
+use bump::{
+    bump_version::{prerelease_identifier, BumpType, BumpVersion},
+    cli::{
+        edit_in_editor, print_commit_summary, prompt_tag_conflict, prompt_version_file,
+        prompt_version_select, PromptOption, TagConflictResolution,
+    },
+    commit_log::render_changelog_section,
+    file_backup::FileBackup,
+    registry::Registry,
+    repo::Repo,
+    settings::{BumpFile, Settings, TagStyle},
+};
 use clap::{value_parser, Arg, ArgAction, Command, ValueEnum};
-use clap_complete::{generate, Generator, Shell};
-use cli::prompt_version_select;
-use config::Config;
-use log::{debug, info};
+use clap_complete::{generate, generate_to, Generator, Shell};
+use clap_mangen::Man;
+use log::{debug, info, warn};
 use owo_colors::{colors::xterm, OwoColorize};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 
 use std::{
     env,
-    fs::File,
+    fs::{self, File},
     io,
     path::{Path, PathBuf},
 };
 
-pub mod bump_version;
-pub mod cli;
-pub mod repo;
-pub mod settings;
-
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, ValueEnum, PartialOrd, Ord)]
 pub enum Action {
     /// Make new commit for changes
     Commit,
     /// Tag the latest commit
     Tag,
+    /// Publish the package to the registry after tagging
+    Publish,
 }
 
 fn cli() -> Command {
@@ -40,6 +46,13 @@ fn cli() -> Command {
                 .help("which version to bump to")
                 .value_parser(value_parser!(BumpType)),
         )
+        .arg(
+            Arg::new("from_labels")
+                .long("from-labels")
+                .help("derive bump type from labels of GitLab merge requests since the last tag")
+                .conflicts_with_all(["bump_type", "promote"])
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("project_path")
                 .long("path")
@@ -48,6 +61,14 @@ fn cli() -> Command {
                 .required(false)
                 .value_parser(value_parser!(PathBuf)),
         )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("use version file, bump files and tag prefix of the profile in settings")
+                .required(false)
+                .value_parser(value_parser!(String)),
+        )
         .arg(
             Arg::new("pre_id")
                 .long("pre-id")
@@ -61,14 +82,64 @@ prerelease version will be -IDENTIFIER.0 or -0",
                 .default_missing_value("")
                 .value_parser(value_parser!(String)),
         )
+        .arg(
+            Arg::new("promote")
+                .long("promote")
+                .help(
+                    "promote prerelease to the next channel, \
+release if it is on the last channel",
+                )
+                .conflicts_with("bump_type")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .help(
+                    "print a snapshot version from date and commit of HEAD, \
+without changing any file",
+                )
+                .conflicts_with("promote")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hide_prerelease")
+                .long("hide-prerelease")
+                .help("hide pre-* options in the version prompt")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("skip")
                 .long("skip")
                 .value_name("ACTION")
-                .help("skip commit or tag")
+                .help("skip commit, tag or publish")
                 .action(clap::ArgAction::Append)
                 .value_parser(value_parser!(Action)),
         )
+        .arg(
+            Arg::new("no_git")
+                .long("no-git")
+                .help("only bump version files, do not stage, commit or tag")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("lightweight")
+                .long("lightweight")
+                .help("create lightweight tags instead of annotated tags")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no_verify")
+                .long("no-verify")
+                .help("skip git hooks when committing")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("fail instead of warning when the version is already published")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("dryrun")
                 .long("dryrun")
@@ -76,12 +147,30 @@ prerelease version will be -IDENTIFIER.0 or -0",
                 .action(clap::ArgAction::SetTrue),
         )
         .subcommand(
-            Command::new("completions").arg(
-                Arg::new("shell")
-                    .long("shell")
-                    .action(ArgAction::Set)
-                    .value_parser(value_parser!(Shell)),
-            ),
+            Command::new("generate")
+                .about("generate shell completions and man page")
+                .visible_alias("completions")
+                .arg(
+                    Arg::new("shell")
+                        .long("shell")
+                        .help("print completions of the shell")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(Shell)),
+                )
+                .arg(
+                    Arg::new("man")
+                        .long("man")
+                        .help("print man page")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("out_dir")
+                        .long("out-dir")
+                        .value_name("DIR")
+                        .help("write man page and completions of all shells to the directory")
+                        .conflicts_with_all(["shell", "man"])
+                        .value_parser(value_parser!(PathBuf)),
+                ),
         )
 }
 
@@ -89,18 +178,64 @@ fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
 
+fn write_generated_files(out_dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(out_dir)?;
+
+    let mut cmd = cli();
+    for shell in Shell::value_variants() {
+        let path = generate_to(*shell, &mut cmd, "bump", out_dir)?;
+        println!("{}", path.to_string_lossy());
+    }
+
+    let man_path = out_dir.join("bump.1");
+    let mut man_file = File::create(&man_path)?;
+    Man::new(cli()).render(&mut man_file)?;
+    println!("{}", man_path.to_string_lossy());
+
+    Ok(())
+}
+
+/// Picks the manifest to read the version from when `version_file` is not configured,
+/// asking if there are several and remembering the answer in `bump.toml`.
+fn detect_version_file(project_repo: &Repo, dry_run: bool) -> anyhow::Result<BumpFile> {
+    let candidates = project_repo.version_file_candidates();
+    let version_file = match candidates.as_slice() {
+        [] => "package.json",
+        [version_file] => version_file,
+        _ => {
+            let version_file = prompt_version_file(&candidates).ok_or_else(|| {
+                anyhow!(
+                    "found {}, set version_file in bump.toml",
+                    candidates.join(" and ")
+                )
+            })?;
+            if !dry_run {
+                Settings::save_version_file(&project_repo.directory, version_file)?;
+                info!("saved version_file = {version_file} in bump.toml");
+            }
+            version_file
+        }
+    };
+
+    Ok(BumpFile::new(version_file))
+}
+
 fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_default_env().init();
 
     let matches = cli().get_matches();
 
-    if let Some(("completions", completions_matches)) = matches.subcommand() {
-        if let Some(shell) = completions_matches.get_one::<Shell>("shell").copied() {
+    if let Some(("generate", generate_matches)) = matches.subcommand() {
+        if let Some(out_dir) = generate_matches.get_one::<PathBuf>("out_dir") {
+            write_generated_files(out_dir)?;
+        } else if let Some(shell) = generate_matches.get_one::<Shell>("shell").copied() {
             let mut cmd = cli();
 
             print_completions(shell, &mut cmd);
+        } else if generate_matches.get_flag("man") {
+            Man::new(cli()).render(&mut io::stdout())?;
         } else {
-            eprintln!("cannot generate auto completions");
+            eprintln!("specify --shell, --man or --out-dir to generate");
         }
         return Ok(());
     }
@@ -111,45 +246,86 @@ fn main() -> anyhow::Result<()> {
         Repo::new(env::current_dir()?)?
     };
 
-    let settings: Settings = Config::builder()
-        .add_source(config::File::from(project_repo.directory.join("bump")).required(false))
-        .build()?
-        .try_deserialize::<Settings>()?;
+    let settings = Settings::load(&project_repo.directory)?;
 
-    let package_json_file_name = "package.json";
-
-    let package_json_file = File::open(project_repo.directory.join(package_json_file_name))?;
-    let package_json: serde_json::Value = serde_json::from_reader(package_json_file)?;
+    let settings = match matches.get_one::<String>("profile") {
+        Some(profile_name) => settings.with_profile(profile_name)?,
+        None => settings,
+    };
 
-    let version = if let Some(version_value) = package_json.get("version") {
-        let version_str = version_value
-            .as_str()
-            .expect("it should be able to convert to str");
-        Version::parse(version_str)?
+    let settings = if settings.branches.is_empty() {
+        settings
     } else {
-        bail!("cannot find version in package.json");
+        match project_repo.current_branch() {
+            Ok(branch) => settings.with_branch(&branch),
+            Err(error) => {
+                warn!("cannot get current branch: {error}");
+                settings
+            }
+        }
     };
 
-    let prerelease_identifier = matches
+    let version_file = match &settings.version_file {
+        Some(version_file) => version_file.clone(),
+        None => detect_version_file(&project_repo, matches.get_flag("dryrun"))?,
+    };
+
+    let version = project_repo.read_version(&version_file)?;
+
+    let pre_id = matches
         .get_one::<String>("pre_id")
-        .map(|pre_id| format!("{pre_id}.0"))
-        .unwrap_or("0".to_string());
-
-    let mut next_version = if let Some(bump_type) = matches.get_one::<BumpType>("bump_type") {
-        match bump_type {
-            BumpType::Major => version.increment_major(),
-            BumpType::Minor => version.increment_minor(),
-            BumpType::Patch => version.increment_patch(),
-            BumpType::PreMajor => version
-                .increment_major()
-                .append_prerelease_identifiers(&prerelease_identifier),
-            BumpType::PreMinor => version
-                .increment_minor()
-                .append_prerelease_identifiers(&prerelease_identifier),
-            BumpType::PrePatch => version
-                .increment_patch()
-                .append_prerelease_identifiers(&prerelease_identifier),
-            BumpType::Prerelease => version.increment_prerelease(),
+        .or(settings.pre_id.as_ref())
+        .filter(|pre_id| !pre_id.is_empty())
+        .map(String::as_str);
+    let prerelease_identifier = prerelease_identifier(pre_id);
+
+    if matches.get_flag("snapshot") {
+        let base_version = match matches.get_one::<BumpType>("bump_type") {
+            Some(bump_type) => bump_type.bump(&version, pre_id),
+            None if version.pre.is_empty() => version.increment_patch(),
+            None => version.convert_prerelease_to_release(),
+        };
+        let (date, sha) = project_repo.head_date_and_sha()?;
+        let snapshot_version = settings
+            .snapshot_template
+            .replace("{version}", &base_version.to_string())
+            .replace("{date}", &date)
+            .replace("{sha}", &sha);
+
+        Version::parse(&snapshot_version).with_context(|| {
+            format!("snapshot version {snapshot_version} from template is not semver")
+        })?;
+        println!("{snapshot_version}");
+        return Ok(());
+    }
+
+    let mut next_version = if matches.get_flag("promote") {
+        if version.pre.is_empty() {
+            bail!("{version} is not a prerelease, nothing to promote");
+        }
+        match version.promote_prerelease(&settings.prerelease_channels) {
+            Some(promoted_version) => promoted_version,
+            None => bail!(
+                "prerelease of {version} is not in channels {:?}",
+                settings.prerelease_channels
+            ),
+        }
+    } else if let Some(bump_type) = matches.get_one::<BumpType>("bump_type") {
+        bump_type.bump(&version, pre_id)
+    } else if matches.get_flag("from_labels") {
+        let Some(gitlab_settings) = &settings.gitlab else {
+            bail!("--from-labels requires gitlab in settings");
+        };
+        let since = match project_repo.last_tag(&settings.tag_prefix) {
+            Some(last_tag) => Some(project_repo.tag_date(&last_tag)?),
+            None => None,
+        };
+        match gitlab_settings.bump_type_from_merge_requests(since)? {
+            Some(bump_type) => bump_type.bump(&version, pre_id),
+            None => {
+                info!("no merge request labeled with semver");
+                version.clone()
+            }
         }
     } else {
         version.clone()
@@ -157,7 +333,18 @@ fn main() -> anyhow::Result<()> {
 
     if version == next_version {
         debug!("no change in version, prompt");
-        next_version = prompt_version_select(&version, &prerelease_identifier);
+        let last_tag = project_repo.last_tag(&settings.tag_prefix);
+        match project_repo.commit_subjects_since(last_tag.as_deref()) {
+            Ok(subjects) => print_commit_summary(last_tag.as_deref(), &subjects),
+            Err(error) => warn!("cannot read commit log: {error}"),
+        }
+        let prompt_options: Vec<PromptOption> = settings
+            .prompt_options
+            .iter()
+            .filter(|option| !(matches.get_flag("hide_prerelease") && option.is_prerelease()))
+            .copied()
+            .collect();
+        next_version = prompt_version_select(&version, &prerelease_identifier, &prompt_options);
     }
 
     if version == next_version {
@@ -165,16 +352,82 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let next_version = next_version.to_string();
-
     let mut skip_actions: Vec<Action> = matches
         .get_many::<Action>("skip")
         .unwrap_or_default()
         .copied()
         .collect();
+    let no_git = matches.get_flag("no_git");
+    if no_git {
+        skip_actions.extend([Action::Commit, Action::Tag]);
+    }
     skip_actions.sort();
     skip_actions.dedup();
 
+    let mut force_tag = false;
+    let will_tag = !skip_actions.contains(&Action::Commit) && !skip_actions.contains(&Action::Tag);
+    let tag = format!("{}{next_version}", settings.tag_prefix);
+    if will_tag && project_repo.tag_exists(&tag)? {
+        debug!("tag {tag} exists, find the next available version");
+        let mut next_available_version = next_version.clone();
+        loop {
+            next_available_version = if next_available_version.pre.is_empty() {
+                next_available_version.increment_patch()
+            } else {
+                next_available_version.increment_prerelease()
+            };
+            if !project_repo
+                .tag_exists(&format!("{}{next_available_version}", settings.tag_prefix))?
+            {
+                break;
+            }
+        }
+
+        match prompt_tag_conflict(&tag, next_available_version) {
+            TagConflictResolution::Abort => bail!("tag {tag} already exists"),
+            TagConflictResolution::ForceMove => force_tag = true,
+            TagConflictResolution::UseVersion(version) => next_version = version,
+        }
+    }
+
+    let publish_command = if settings.publish && !skip_actions.contains(&Action::Publish) {
+        match settings.registry {
+            Some(registry) => Some(registry.publish_command()),
+            None => bail!("publish requires registry in settings"),
+        }
+    } else {
+        None
+    };
+
+    if let Some(registry) = settings.registry {
+        let package_name = match registry {
+            Registry::Npm => project_repo.npm_package_name()?,
+            Registry::CratesIo => project_repo.cargo_package_name()?,
+        };
+
+        match registry.is_published(&package_name, &next_version.to_string()) {
+            Ok(false) => {}
+            Ok(true) if matches.get_flag("strict") => {
+                bail!("{package_name}@{next_version} is already published")
+            }
+            Ok(true) => warn!("{package_name}@{next_version} is already published"),
+            Err(error) => warn!("{error}"),
+        }
+    }
+
+    // floating tags follow releases only
+    let extra_tags: Vec<String> = if next_version.pre.is_empty() {
+        settings
+            .extra_tags
+            .iter()
+            .map(|extra_tag| extra_tag.tag_name(&settings.tag_prefix, &next_version))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let next_version = next_version.to_string();
+
     if matches.get_flag("dryrun") {
         println!(
             "{} {}{}",
@@ -183,8 +436,18 @@ fn main() -> anyhow::Result<()> {
             next_version.green()
         );
 
-        let file_names = std::iter::once(package_json_file_name.to_string())
-            .chain(settings.bump_files)
+        let file_names = std::iter::once(version_file.path)
+            .chain(
+                settings
+                    .bump_files
+                    .into_iter()
+                    .map(|bump_file| bump_file.path),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let commit_file_names = std::iter::once(file_names.clone())
+            .chain(settings.stage_extra_files)
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -194,42 +457,154 @@ fn main() -> anyhow::Result<()> {
             file_names.green(),
         );
 
+        if settings.sync_dependents {
+            println!(
+                "{}",
+                "will update dependents in npm workspace".bg::<xterm::Gray>(),
+            );
+        }
+
+        if let Some(changelog_file) = &settings.changelog_file {
+            println!(
+                "{} {}",
+                "will add release notes to".bg::<xterm::Gray>(),
+                changelog_file.green()
+            );
+        }
+
+        if let Some(verify_command) = &settings.verify_command {
+            println!(
+                "{} {}",
+                "will verify with".bg::<xterm::Gray>(),
+                verify_command.green()
+            );
+        }
+
         if !skip_actions.contains(&Action::Commit) {
             println!(
                 "{} {}",
                 "will commit files".bg::<xterm::Gray>(),
-                file_names.green()
+                commit_file_names.green()
             );
 
             if !skip_actions.contains(&Action::Tag) {
                 println!("{}", "will tag version".bg::<xterm::Gray>(),);
+
+                if !extra_tags.is_empty() {
+                    println!(
+                        "{} {}",
+                        "will move tags".bg::<xterm::Gray>(),
+                        extra_tags.join(", ").green()
+                    );
+                }
+
+                if let Some(publish_command) = publish_command {
+                    println!(
+                        "{} {}",
+                        "will publish with".bg::<xterm::Gray>(),
+                        publish_command.green()
+                    );
+                }
             }
         }
 
         return Ok(());
     }
 
+    let changelog_section = if settings.changelog_file.is_some() {
+        let last_tag = project_repo.last_tag(&settings.tag_prefix);
+        let subjects = project_repo.commit_subjects_since(last_tag.as_deref())?;
+        let mut section =
+            render_changelog_section(&format!("{}{next_version}", settings.tag_prefix), &subjects);
+
+        if settings.edit_changelog {
+            section = edit_in_editor(&section, "CHANGELOG.md")?;
+            if section.trim().is_empty() {
+                bail!("release notes are empty, abort");
+            }
+        }
+        Some(section)
+    } else {
+        None
+    };
+
+    let mut file_backup = FileBackup::default();
+
     info!("bump to version {}", next_version);
-    project_repo.bump_json(package_json_file_name, &next_version)?;
-    project_repo.stage_file(package_json_file_name)?;
+    file_backup.save(&project_repo.directory.join(&version_file.path))?;
+    project_repo.bump_file(&version_file, &next_version)?;
 
     debug!("bump other files {:?}", settings.bump_files);
 
     for bump_file in settings.bump_files {
-        if !Path::new(&bump_file).exists() {
-            debug!("{bump_file} does not exist, skip.");
+        let bump_file_path = project_repo.directory.join(&bump_file.path);
+        if !bump_file_path.exists() {
+            debug!("{} does not exist, skip.", bump_file.path);
             continue;
         }
 
-        project_repo.bump_json(&bump_file, &next_version)?;
-        project_repo.stage_file(&bump_file)?;
+        file_backup.save(&bump_file_path)?;
+        project_repo.bump_file(&bump_file, &next_version)?;
+    }
+
+    if settings.sync_dependents {
+        let package_name = project_repo.npm_package_name()?;
+        project_repo.sync_dependents(&package_name, &next_version, &mut file_backup)?;
+    }
+
+    if let (Some(changelog_file), Some(changelog_section)) =
+        (&settings.changelog_file, &changelog_section)
+    {
+        file_backup.save(&project_repo.directory.join(changelog_file))?;
+        project_repo.prepend_changelog(changelog_file, changelog_section)?;
+    }
+
+    if let Some(verify_command) = &settings.verify_command {
+        info!("verify release with `{verify_command}`");
+        if let Err(error) = project_repo.run_shell_command(verify_command) {
+            file_backup.restore()?;
+            return Err(error.context("verification failed, bumped files are restored"));
+        }
+    }
+
+    if no_git {
+        debug!("no git, leave bumped files unstaged");
+        return Ok(());
+    }
+
+    for bumped_file in file_backup.paths() {
+        project_repo.stage_file(&bumped_file.to_string_lossy())?;
+    }
+
+    for extra_file in settings.stage_extra_files {
+        if !project_repo.directory.join(&extra_file).exists() {
+            debug!("{extra_file} does not exist, skip.");
+            continue;
+        }
+
+        project_repo.stage_file(&extra_file)?;
     }
 
     if !skip_actions.contains(&Action::Commit) {
-        project_repo.commit_changes(&next_version)?;
+        let no_verify = matches.get_flag("no_verify") || settings.no_verify;
+        project_repo.commit_changes(&next_version, no_verify)?;
 
         if !skip_actions.contains(&Action::Tag) {
-            project_repo.tag_release(&next_version, &settings.tag_prefix)?;
+            let tag_style = if matches.get_flag("lightweight") {
+                TagStyle::Lightweight
+            } else {
+                settings.tag_style
+            };
+            project_repo.tag_release(&next_version, &settings.tag_prefix, tag_style, force_tag)?;
+
+            for extra_tag in &extra_tags {
+                project_repo.move_tag(extra_tag, &next_version, tag_style)?;
+            }
+
+            if let Some(publish_command) = publish_command {
+                info!("publish with `{publish_command}`");
+                project_repo.run_shell_command(publish_command)?;
+            }
         }
     }
 
@@ -0,0 +1,50 @@
+use anyhow::anyhow;
+use log::debug;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Registry {
+    /// registry.npmjs.org
+    Npm,
+    /// crates.io
+    CratesIo,
+}
+
+impl Registry {
+    fn version_url(&self, package_name: &str, version: &str) -> String {
+        match self {
+            Registry::Npm => format!(
+                "https://registry.npmjs.org/{}/{version}",
+                package_name.replace('/', "%2F")
+            ),
+            Registry::CratesIo => {
+                format!("https://crates.io/api/v1/crates/{package_name}/{version}")
+            }
+        }
+    }
+
+    pub fn publish_command(&self) -> &'static str {
+        match self {
+            Registry::Npm => "npm publish",
+            Registry::CratesIo => "cargo publish",
+        }
+    }
+
+    /// Checks whether the version of the package is already published.
+    pub fn is_published(&self, package_name: &str, version: &str) -> anyhow::Result<bool> {
+        let url = self.version_url(package_name, version);
+        debug!("check registry {url}");
+
+        // crates.io rejects requests without user agent
+        let response = ureq::get(&url)
+            .set("User-Agent", concat!("bump/", env!("CARGO_PKG_VERSION")))
+            .call();
+
+        match response {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(404, _)) => Ok(false),
+            Err(error) => Err(anyhow!("cannot check registry: {error}")),
+        }
+    }
+}
@@ -1,67 +1,670 @@
 use anyhow::{anyhow, Context};
-use log::info;
+use chrono::{DateTime, FixedOffset};
+use log::{debug, info, warn};
+use quick_xml::{events::Event, Reader};
+use semver::Version;
 use serde_json::json;
-use std::{fs::File, io::Write, path::PathBuf, process};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+};
+use toml_edit::{value, DocumentMut};
 
+use crate::{
+    file_backup::FileBackup,
+    settings::{BumpFile, TagStyle},
+};
+
+/// Project directory in a git repository, where version files are bumped and released.
 #[derive(Debug, Clone)]
 pub struct Repo {
     pub directory: PathBuf,
 }
 
 impl Repo {
+    /// Opens the project in the directory, fails if it does not exist.
     pub fn new(directory: PathBuf) -> anyhow::Result<Self> {
         info!("create repo struct in {}", directory.to_string_lossy());
         if directory.exists() {
-            Ok(Self { directory })
+            Ok(Self {
+                directory: directory.canonicalize()?,
+            })
         } else {
             Err(anyhow!("{} does not exists.", directory.to_string_lossy()))
         }
     }
 
+    /// Stages the file relative to the project directory.
     pub fn stage_file(&self, file_name: &str) -> anyhow::Result<String> {
         run_git_command(&self.directory, &["add", file_name])
     }
 
-    pub fn commit_changes(&self, next_version: &str) -> anyhow::Result<String> {
+    /// Commits staged changes, `no_verify` skips pre-commit and commit-msg hooks.
+    /// `git tag` does not run hooks so there is no such option for tagging.
+    pub fn commit_changes(&self, next_version: &str, no_verify: bool) -> anyhow::Result<String> {
         let message = format!("chore(release): {next_version}");
-        run_git_command(&self.directory, &["commit", "-m", &message])?;
+        let mut args = vec!["commit", "-m", &message];
+        if no_verify {
+            args.push("--no-verify");
+        }
+        run_git_command(&self.directory, &args)?;
 
         Ok(String::from(""))
     }
 
-    pub fn tag_release(&self, next_version: &str, tag_prefix: &str) -> anyhow::Result<String> {
+    /// Tags HEAD as the release of the version with the tag prefix.
+    pub fn tag_release(
+        &self,
+        next_version: &str,
+        tag_prefix: &str,
+        tag_style: TagStyle,
+        force: bool,
+    ) -> anyhow::Result<String> {
+        let tag = format!("{tag_prefix}{next_version}");
+        self.create_tag(&tag, next_version, tag_style, force)
+    }
+
+    /// Creates the tag or moves it to the current commit if it already exists.
+    pub fn move_tag(
+        &self,
+        tag: &str,
+        next_version: &str,
+        tag_style: TagStyle,
+    ) -> anyhow::Result<String> {
+        self.create_tag(tag, next_version, tag_style, true)
+    }
+
+    fn create_tag(
+        &self,
+        tag: &str,
+        next_version: &str,
+        tag_style: TagStyle,
+        force: bool,
+    ) -> anyhow::Result<String> {
         let message = format!("chore(release): {next_version}");
-        run_git_command(
+        let mut args = vec!["tag", tag];
+        if tag_style == TagStyle::Annotated {
+            args.extend(["-a", "-m", &message]);
+        }
+        if force {
+            args.push("--force");
+        }
+        run_git_command(&self.directory, &args)?;
+
+        Ok(String::from(""))
+    }
+
+    /// Returns commit date as `YYYYMMDD` and short hash of HEAD.
+    pub fn head_date_and_sha(&self) -> anyhow::Result<(String, String)> {
+        let output = run_git_command(
             &self.directory,
             &[
-                "tag",
-                "-a",
-                &format!("{tag_prefix}{next_version}"),
-                "-m",
-                &message,
+                "show",
+                "-s",
+                "--format=%cd %h",
+                "--date=format:%Y%m%d",
+                "HEAD",
             ],
         )?;
 
-        Ok(String::from(""))
+        match output.trim().split_once(' ') {
+            Some((date, sha)) => Ok((date.to_string(), sha.to_string())),
+            None => Err(anyhow!("unexpected output from git show: {output}")),
+        }
     }
 
-    pub fn bump_json(&self, file_path: &str, next_version: &str) -> anyhow::Result<()> {
+    /// Returns the name of the checked out branch, `HEAD` if detached.
+    pub fn current_branch(&self) -> anyhow::Result<String> {
+        let branch = run_git_command(&self.directory, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        Ok(branch.trim().to_string())
+    }
+
+    /// Finds the latest release tag with the prefix reachable from HEAD.
+    pub fn last_tag(&self, tag_prefix: &str) -> Option<String> {
+        let pattern = format!("{tag_prefix}[0-9]*");
+        match run_git_command(
+            &self.directory,
+            &["describe", "--tags", "--abbrev=0", "--match", &pattern],
+        ) {
+            Ok(tag) => Some(tag.trim().to_string()),
+            Err(error) => {
+                debug!("no tag found: {error}");
+                None
+            }
+        }
+    }
+
+    /// Returns the commit date of the tag.
+    pub fn tag_date(&self, tag: &str) -> anyhow::Result<DateTime<FixedOffset>> {
+        let date = run_git_command(&self.directory, &["log", "-1", "--format=%cI", tag])?;
+
+        Ok(DateTime::parse_from_rfc3339(date.trim())?)
+    }
+
+    /// Lists subjects of commits after the tag, or all commits if there is no tag.
+    pub fn commit_subjects_since(&self, tag: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let range = tag.map(|tag| format!("{tag}..HEAD"));
+        let mut args = vec!["log", "--format=%s"];
+        if let Some(range) = &range {
+            args.push(range);
+        }
+        let subjects = run_git_command(&self.directory, &args)?;
+
+        Ok(subjects.lines().map(String::from).collect())
+    }
+
+    /// Checks whether the tag exists locally or on any of the remotes.
+    pub fn tag_exists(&self, tag: &str) -> anyhow::Result<bool> {
+        let local_tags = run_git_command(&self.directory, &["tag", "--list", tag])?;
+        if !local_tags.trim().is_empty() {
+            return Ok(true);
+        }
+
+        let remotes = run_git_command(&self.directory, &["remote"])?;
+        for remote in remotes.lines() {
+            let tag_ref = format!("refs/tags/{tag}");
+            match run_git_command(&self.directory, &["ls-remote", "--tags", remote, &tag_ref]) {
+                Ok(remote_tags) if !remote_tags.trim().is_empty() => return Ok(true),
+                Ok(_) => {}
+                Err(error) => warn!("cannot list tags of remote {remote}: {error}"),
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Writes the version to the file, choosing the format by the file name.
+    pub fn bump_file(&self, bump_file: &BumpFile, next_version: &str) -> anyhow::Result<()> {
+        let path = Path::new(&bump_file.path);
+        let version_value = format!("{}{next_version}", bump_file.value_prefix);
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some("Cargo.lock") => self.bump_cargo_lock(&bump_file.path, next_version),
+            _ if path
+                .extension()
+                .is_some_and(|extension| extension == "toml") =>
+            {
+                self.bump_toml(
+                    &bump_file.path,
+                    &bump_file.version_toml_path,
+                    &version_value,
+                )
+            }
+            _ if is_xml(path) => self.bump_xml(
+                &bump_file.path,
+                &version_xml_path(bump_file),
+                &version_value,
+            ),
+            _ => self.bump_json(
+                &bump_file.path,
+                &bump_file.version_json_pointer,
+                &version_value,
+            ),
+        }
+    }
+
+    /// Sets the value at the JSON pointer, skips the file if the pointer does not exist.
+    pub fn bump_json(
+        &self,
+        file_path: &str,
+        version_pointer: &str,
+        next_version: &str,
+    ) -> anyhow::Result<()> {
         info!("bump {} to {}", file_path, next_version);
         let full_path = self.directory.join(file_path);
         let json_file = File::open(&full_path)?;
         let mut package_json: serde_json::Value = serde_json::from_reader(json_file)?;
 
-        if let Some(version) = package_json.get_mut("version") {
-            *version = json!(next_version);
+        match package_json.pointer_mut(version_pointer) {
+            Some(version) => *version = json!(next_version),
+            None => {
+                warn!("cannot find version at {version_pointer} in {file_path}, skip.");
+                return Ok(());
+            }
+        }
+
+        write_json(&full_path, &package_json)
+    }
+
+    /// Rewrites dependency ranges on `package_name` in the other packages of the npm
+    /// workspace containing this repo.
+    pub fn sync_dependents(
+        &self,
+        package_name: &str,
+        next_version: &str,
+        file_backup: &mut FileBackup,
+    ) -> anyhow::Result<()> {
+        let Some((workspace_root, patterns)) = find_npm_workspace(&self.directory)? else {
+            debug!(
+                "{} is not in a npm workspace",
+                self.directory.to_string_lossy()
+            );
+            return Ok(());
+        };
+
+        for pattern in patterns {
+            let pattern = workspace_root.join(pattern);
+            for member_directory in glob::glob(&pattern.to_string_lossy())?.flatten() {
+                let member_directory = member_directory.canonicalize()?;
+                let package_json_path = member_directory.join("package.json");
+                if member_directory == self.directory || !package_json_path.exists() {
+                    continue;
+                }
+
+                let mut package_json: serde_json::Value =
+                    serde_json::from_reader(File::open(&package_json_path)?)?;
+                if update_dependency_ranges(&mut package_json, package_name, next_version) {
+                    info!(
+                        "update {} in {}",
+                        package_name,
+                        package_json_path.to_string_lossy()
+                    );
+                    file_backup.save(&package_json_path)?;
+                    write_json(&package_json_path, &package_json)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists manifests in the project that can be the version file.
+    pub fn version_file_candidates(&self) -> Vec<&'static str> {
+        ["package.json", "Cargo.toml"]
+            .into_iter()
+            .filter(|file_name| self.directory.join(file_name).is_file())
+            .collect()
+    }
+
+    /// Reads the version from the version file, without the value prefix.
+    pub fn read_version(&self, version_file: &BumpFile) -> anyhow::Result<Version> {
+        let full_path = self.directory.join(&version_file.path);
+        let version_value = if is_toml(&full_path) {
+            let manifest = read_toml(&full_path)?;
+            version_file
+                .version_toml_path
+                .split('.')
+                .try_fold(manifest.as_item(), |item, key| item.get(key))
+                .and_then(|version| version.as_str())
+                .map(String::from)
+        } else if is_xml(&full_path) {
+            let content = fs::read_to_string(&full_path)
+                .with_context(|| format!("cannot read {}", version_file.path))?;
+            find_xml_text(&content, &version_xml_path(version_file))?
+                .map(|range| content[range].to_string())
+        } else {
+            let json: serde_json::Value = serde_json::from_reader(
+                File::open(&full_path)
+                    .with_context(|| format!("cannot open {}", version_file.path))?,
+            )?;
+            json.pointer(&version_file.version_json_pointer)
+                .and_then(|version| version.as_str())
+                .map(String::from)
+        };
+
+        let Some(version_value) = version_value else {
+            return Err(anyhow!("cannot find version in {}", version_file.path));
+        };
+        let version_str = version_value
+            .strip_prefix(&version_file.value_prefix)
+            .unwrap_or(&version_value);
+
+        Ok(Version::parse(version_str)?)
+    }
+
+    /// Reads the package name in package.json.
+    pub fn npm_package_name(&self) -> anyhow::Result<String> {
+        let package_json: serde_json::Value =
+            serde_json::from_reader(File::open(self.directory.join("package.json"))?)?;
+        package_json
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow!("cannot find name in package.json"))
+    }
+
+    /// Reads the package name in Cargo.toml.
+    pub fn cargo_package_name(&self) -> anyhow::Result<String> {
+        let manifest = read_toml(&self.directory.join("Cargo.toml"))?;
+        manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow!("cannot find package name in Cargo.toml"))
+    }
+
+    /// Inserts the section at the top of the changelog, below the `# ` title if any.
+    pub fn prepend_changelog(&self, file_path: &str, section: &str) -> anyhow::Result<()> {
+        info!("update changelog {}", file_path);
+        let full_path = self.directory.join(file_path);
+        let changelog = if full_path.exists() {
+            fs::read_to_string(&full_path)?
+        } else {
+            String::from("# Changelog\n")
+        };
+
+        let (title, rest) = match changelog.split_once('\n') {
+            Some((title, rest)) if title.starts_with("# ") => (format!("{title}\n\n"), rest),
+            _ => (String::new(), changelog.as_str()),
+        };
+        let rest = rest.trim_start_matches('\n');
+        let separator = if rest.is_empty() { "" } else { "\n" };
+
+        fs::write(
+            &full_path,
+            format!("{title}{}\n{separator}{rest}", section.trim_end()),
+        )?;
+
+        Ok(())
+    }
+
+    /// Runs the command in shell with inherited stdio, fails if it exits with non-zero.
+    pub fn run_shell_command(&self, command: &str) -> anyhow::Result<()> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let status = process::Command::new(shell)
+            .arg(flag)
+            .arg(command)
+            .current_dir(&self.directory)
+            .status()
+            .with_context(|| format!("error while running `{command}`"))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("`{command}` exited with {status}"))
+        }
+    }
+
+    /// Sets the value at the dotted path, keeping formatting and comments of the file.
+    pub fn bump_toml(
+        &self,
+        file_path: &str,
+        version_path: &str,
+        next_version: &str,
+    ) -> anyhow::Result<()> {
+        info!("bump {} to {}", file_path, next_version);
+        let full_path = self.directory.join(file_path);
+        let mut manifest = read_toml(&full_path)?;
+
+        let version = version_path
+            .split('.')
+            .try_fold(manifest.as_item_mut(), |item, key| item.get_mut(key));
+
+        match version.and_then(|version| version.as_value_mut()) {
+            // `version.workspace = true` is not a string, leave it to the workspace
+            Some(version) if version.is_str() => {
+                // keep comments and spaces around the value
+                let decor = version.decor().clone();
+                *version = next_version.into();
+                *version.decor_mut() = decor;
+            }
+            _ => {
+                warn!("cannot find version at {version_path} in {file_path}, skip.");
+                return Ok(());
+            }
         }
 
-        let mut file = File::create(&full_path)?;
-        let updated_package_json_str = serde_json::to_string_pretty(&package_json)?;
+        fs::write(&full_path, manifest.to_string())?;
 
-        file.write_all(updated_package_json_str.as_bytes())?;
+        Ok(())
+    }
+
+    /// Sets the text of the element at the slash separated path, like `project/version`,
+    /// leaving the rest of the file untouched.
+    pub fn bump_xml(
+        &self,
+        file_path: &str,
+        element_path: &str,
+        next_version: &str,
+    ) -> anyhow::Result<()> {
+        info!("bump {} to {}", file_path, next_version);
+        let full_path = self.directory.join(file_path);
+        let mut content =
+            fs::read_to_string(&full_path).with_context(|| format!("cannot read {file_path}"))?;
+
+        let Some(range) = find_xml_text(&content, element_path)
+            .with_context(|| format!("cannot parse {file_path}"))?
+        else {
+            warn!("cannot find version at {element_path} in {file_path}, skip.");
+            return Ok(());
+        };
+        content.replace_range(range, next_version);
+
+        fs::write(&full_path, content)?;
 
         Ok(())
     }
+
+    /// Updates version of the `[[package]]` entries in Cargo.lock that belong to the
+    /// crate next to it, and workspace members inheriting the workspace version.
+    pub fn bump_cargo_lock(&self, file_path: &str, next_version: &str) -> anyhow::Result<()> {
+        info!("bump {} to {}", file_path, next_version);
+        let full_path = self.directory.join(file_path);
+        let manifest_directory = full_path.parent().unwrap_or(&self.directory);
+        let own_packages = cargo_own_packages(manifest_directory)?;
+        debug!("own packages in {}: {:?}", file_path, own_packages);
+
+        let mut lock_file = fs::read_to_string(&full_path)?.parse::<DocumentMut>()?;
+
+        if let Some(packages) = lock_file
+            .get_mut("package")
+            .and_then(|item| item.as_array_of_tables_mut())
+        {
+            for package in packages.iter_mut() {
+                // packages from registry or git have a source, local ones do not
+                if package.contains_key("source") {
+                    continue;
+                }
+                let is_own_package = package
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .is_some_and(|name| own_packages.contains(name));
+                if is_own_package {
+                    package["version"] = value(next_version);
+                }
+            }
+        }
+
+        fs::write(&full_path, lock_file.to_string())?;
+
+        Ok(())
+    }
+}
+
+fn write_json(path: &Path, json: &serde_json::Value) -> anyhow::Result<()> {
+    let mut file = File::create(path)?;
+    let json_str = serde_json::to_string_pretty(json)?;
+
+    file.write_all(json_str.as_bytes())?;
+
+    Ok(())
+}
+
+/// Finds the closest ancestor package.json declaring `workspaces`, returns its directory
+/// and the workspace patterns.
+fn find_npm_workspace(directory: &Path) -> anyhow::Result<Option<(PathBuf, Vec<String>)>> {
+    for ancestor in directory.ancestors() {
+        let package_json_path = ancestor.join("package.json");
+        if !package_json_path.exists() {
+            continue;
+        }
+        let package_json: serde_json::Value =
+            serde_json::from_reader(File::open(&package_json_path)?)?;
+
+        // yarn also accepts `{ "packages": [...] }`
+        let workspaces = package_json.get("workspaces").map(|workspaces| {
+            workspaces
+                .get("packages")
+                .unwrap_or(workspaces)
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|pattern| pattern.as_str().map(String::from))
+                .collect::<Vec<_>>()
+        });
+
+        if let Some(workspaces) = workspaces {
+            return Ok(Some((ancestor.to_path_buf(), workspaces)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Replaces the version in ranges like `^1.2.3` on `package_name`, keeping the range operator.
+fn update_dependency_ranges(
+    package_json: &mut serde_json::Value,
+    package_name: &str,
+    next_version: &str,
+) -> bool {
+    let mut updated = false;
+    for dependency_type in [
+        "dependencies",
+        "devDependencies",
+        "peerDependencies",
+        "optionalDependencies",
+    ] {
+        let Some(range) = package_json
+            .get_mut(dependency_type)
+            .and_then(|dependencies| dependencies.get_mut(package_name))
+        else {
+            continue;
+        };
+        let Some(range_str) = range.as_str() else {
+            continue;
+        };
+        let Some(version_start) = range_str.find(|c: char| c.is_ascii_digit()) else {
+            continue;
+        };
+        let (operator, version) = range_str.split_at(version_start);
+        if semver::Version::parse(version).is_err() {
+            debug!("cannot update complex range {range_str} of {package_name}");
+            continue;
+        }
+
+        *range = json!(format!("{operator}{next_version}"));
+        updated = true;
+    }
+
+    updated
+}
+
+/// Collects names of the root package and workspace members sharing the workspace version.
+fn cargo_own_packages(manifest_directory: &Path) -> anyhow::Result<HashSet<String>> {
+    let manifest = read_toml(&manifest_directory.join("Cargo.toml"))?;
+    let mut names = HashSet::new();
+
+    if let Some(name) = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+    {
+        names.insert(name.to_string());
+    }
+
+    let members = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array());
+
+    for member in members.into_iter().flatten().filter_map(|m| m.as_str()) {
+        let pattern = manifest_directory.join(member);
+        for member_directory in glob::glob(&pattern.to_string_lossy())?.flatten() {
+            let member_manifest = read_toml(&member_directory.join("Cargo.toml"))?;
+            let Some(package) = member_manifest.get("package") else {
+                continue;
+            };
+            let inherits_version = package
+                .get("version")
+                .and_then(|version| version.get("workspace"))
+                .and_then(|workspace| workspace.as_bool())
+                .unwrap_or(false);
+            if !inherits_version {
+                continue;
+            }
+            if let Some(name) = package.get("name").and_then(|name| name.as_str()) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+fn is_xml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            matches!(extension, "xml" | "csproj" | "fsproj" | "vbproj" | "props")
+        })
+}
+
+/// Version element of Maven pom.xml by default, or of .NET project files.
+fn version_xml_path(bump_file: &BumpFile) -> String {
+    if let Some(element_path) = &bump_file.version_xml_path {
+        return element_path.clone();
+    }
+    if Path::new(&bump_file.path)
+        .extension()
+        .is_some_and(|extension| extension != "xml")
+    {
+        "Project/PropertyGroup/Version".to_string()
+    } else {
+        "project/version".to_string()
+    }
+}
+
+/// Finds the byte range of the trimmed text of the first element at the slash separated
+/// path from the root, like `project/version`.
+fn find_xml_text(content: &str, element_path: &str) -> anyhow::Result<Option<Range<usize>>> {
+    let target: Vec<&str> = element_path.split('/').collect();
+    let mut reader = Reader::from_str(content);
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        match reader.read_event()? {
+            Event::Start(element) => stack.push(element.local_name().as_ref().to_vec()),
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(_)
+                if stack.len() == target.len()
+                    && stack
+                        .iter()
+                        .zip(&target)
+                        .all(|(name, key)| name == key.as_bytes()) =>
+            {
+                let end = reader.buffer_position() as usize;
+                let text = &content[start..end];
+                let text_start = start + (text.len() - text.trim_start().len());
+                return Ok(Some(text_start..text_start + text.trim().len()));
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn read_toml(path: &Path) -> anyhow::Result<DocumentMut> {
+    fs::read_to_string(path)
+        .with_context(|| format!("cannot read {}", path.to_string_lossy()))?
+        .parse::<DocumentMut>()
+        .with_context(|| format!("cannot parse {}", path.to_string_lossy()))
 }
 
 fn run_git_command(dir: &PathBuf, args: &[&str]) -> anyhow::Result<String> {
@@ -1,17 +1,266 @@
+use anyhow::{anyhow, Context};
+use config::Config;
+use log::debug;
+use semver::Version;
 use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+use toml_edit::{value, DocumentMut};
 
+use crate::{cli::PromptOption, gitlab::GitlabSettings, registry::Registry};
+
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraTag {
+    /// Floating tag of the major version like `v1`
+    Major,
+    /// Floating tag of the minor version like `v1.4`
+    Minor,
+}
+
+impl ExtraTag {
+    pub fn tag_name(&self, tag_prefix: &str, version: &Version) -> String {
+        match self {
+            ExtraTag::Major => format!("{tag_prefix}{}", version.major),
+            ExtraTag::Minor => format!("{tag_prefix}{}.{}", version.major, version.minor),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagStyle {
+    /// Tag with message, `git tag -a`
+    #[default]
+    Annotated,
+    /// Tag as a plain reference to the commit
+    Lightweight,
+}
+
+/// A file to bump along with package.json, configured as a path or a table with
+/// the path and where the version is in the file.
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[serde(from = "BumpFileEntry")]
+pub struct BumpFile {
+    pub path: String,
+    /// JSON pointer to the version, like `/project/version`
+    pub version_json_pointer: String,
+    /// dotted path to the version in TOML, like `tool.poetry.version`
+    pub version_toml_path: String,
+    /// slash separated path to the version element in XML, like `project/version`,
+    /// defaults by file type for pom.xml and .NET project files
+    pub version_xml_path: Option<String>,
+    /// prefix kept before the version, like `v` in `"version": "v1.2.3"`
+    pub value_prefix: String,
+}
+
+impl BumpFile {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            version_json_pointer: default_version_json_pointer(),
+            version_toml_path: default_version_toml_path(),
+            version_xml_path: None,
+            value_prefix: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BumpFileEntry {
+    Path(String),
+    Detailed {
+        path: String,
+        #[serde(default = "default_version_json_pointer")]
+        version_json_pointer: String,
+        #[serde(default = "default_version_toml_path")]
+        version_toml_path: String,
+        version_xml_path: Option<String>,
+        #[serde(default)]
+        value_prefix: String,
+    },
+}
+
+impl From<BumpFileEntry> for BumpFile {
+    fn from(entry: BumpFileEntry) -> Self {
+        match entry {
+            BumpFileEntry::Path(path) => BumpFile::new(&path),
+            BumpFileEntry::Detailed {
+                path,
+                version_json_pointer,
+                version_toml_path,
+                version_xml_path,
+                value_prefix,
+            } => BumpFile {
+                path,
+                version_json_pointer,
+                version_toml_path,
+                version_xml_path,
+                value_prefix,
+            },
+        }
+    }
+}
+
+fn default_version_json_pointer() -> String {
+    "/version".to_string()
+}
+
+fn default_version_toml_path() -> String {
+    "package.version".to_string()
+}
+
+/// Overrides of settings to release an artifact with its own version line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub version_file: Option<BumpFile>,
+    pub bump_files: Option<Vec<BumpFile>>,
+    pub tag_prefix: Option<String>,
+}
+
+/// Overrides of settings applied when the current branch matches the pattern.
+#[derive(Debug, Deserialize)]
+pub struct BranchSettings {
+    /// glob pattern of branch names, like `release/*`
+    pub pattern: String,
+    pub tag_prefix: Option<String>,
+    pub pre_id: Option<String>,
+}
+
+/// Project settings read from `bump.toml`.
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Settings {
-    pub bump_files: Vec<String>,
+    /// file to read the current version from, bumped as well, package.json or
+    /// Cargo.toml in the project if not set
+    pub version_file: Option<BumpFile>,
+    pub bump_files: Vec<BumpFile>,
     pub tag_prefix: String,
+    pub tag_style: TagStyle,
+    /// default identifier for prerelease when `--pre-id` is not given
+    pub pre_id: Option<String>,
+    /// prerelease channels in the order of promotion
+    pub prerelease_channels: Vec<String>,
+    /// update dependency ranges on this package in other npm workspace packages
+    pub sync_dependents: bool,
+    /// floating tags moved to every release
+    pub extra_tags: Vec<ExtraTag>,
+    /// files staged with the release commit if present, like CHANGELOG.md
+    pub stage_extra_files: Vec<String>,
+    /// command to run after files are bumped, the release is aborted if it fails
+    pub verify_command: Option<String>,
+    /// registry to check whether the version is already published
+    pub registry: Option<Registry>,
+    /// options shown in the version prompt, in order
+    pub prompt_options: Vec<PromptOption>,
+    /// skip git hooks when committing the release
+    pub no_verify: bool,
+    /// markdown file to add release notes generated from commits, like CHANGELOG.md
+    pub changelog_file: Option<String>,
+    /// open the generated release notes in editor before committing
+    pub edit_changelog: bool,
+    /// publish to the registry after tagging
+    pub publish: bool,
+    /// named profiles selected with `--profile`
+    pub profile: HashMap<String, Profile>,
+    /// overrides by branch, the first matching one is applied
+    pub branches: Vec<BranchSettings>,
+    /// template of `--snapshot` version with `{version}`, `{date}` and `{sha}`
+    pub snapshot_template: String,
+    /// GitLab project to derive bump type from merge request labels
+    pub gitlab: Option<GitlabSettings>,
+}
+
+impl Settings {
+    /// Loads `bump.toml` in the directory, or defaults if there is none.
+    pub fn load(directory: &Path) -> anyhow::Result<Self> {
+        Ok(Config::builder()
+            .add_source(config::File::from(directory.join("bump")).required(false))
+            .build()?
+            .try_deserialize::<Self>()?)
+    }
+
+    /// Writes `version_file` to `bump.toml` in the directory, keeping the rest of the file.
+    pub fn save_version_file(directory: &Path, version_file: &str) -> anyhow::Result<()> {
+        let path = directory.join("bump.toml");
+        let mut document = if path.exists() {
+            fs::read_to_string(&path)?
+                .parse::<DocumentMut>()
+                .context("cannot parse bump.toml")?
+        } else {
+            DocumentMut::new()
+        };
+        document["version_file"] = value(version_file);
+
+        Ok(fs::write(&path, document.to_string())?)
+    }
+
+    /// Applies overrides of the profile.
+    pub fn with_profile(mut self, profile_name: &str) -> anyhow::Result<Self> {
+        let profile = self
+            .profile
+            .remove(profile_name)
+            .ok_or_else(|| anyhow!("cannot find profile {profile_name} in settings"))?;
+
+        if let Some(version_file) = profile.version_file {
+            self.version_file = Some(version_file);
+        }
+        if let Some(bump_files) = profile.bump_files {
+            self.bump_files = bump_files;
+        }
+        if let Some(tag_prefix) = profile.tag_prefix {
+            self.tag_prefix = tag_prefix;
+        }
+
+        Ok(self)
+    }
+
+    /// Applies overrides of the first branch settings matching the branch.
+    pub fn with_branch(mut self, branch: &str) -> Self {
+        let matched_branch = self.branches.iter().position(|branch_settings| {
+            glob::Pattern::new(&branch_settings.pattern)
+                .is_ok_and(|pattern| pattern.matches(branch))
+        });
+
+        if let Some(index) = matched_branch {
+            let branch_settings = self.branches.remove(index);
+            debug!("apply settings of {} to {branch}", branch_settings.pattern);
+            if let Some(tag_prefix) = branch_settings.tag_prefix {
+                self.tag_prefix = tag_prefix;
+            }
+            if let Some(pre_id) = branch_settings.pre_id {
+                self.pre_id = Some(pre_id);
+            }
+        }
+
+        self
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
-            bump_files: vec!["package-lock.json".to_string()],
+            version_file: None,
+            bump_files: vec![BumpFile::new("package-lock.json")],
             tag_prefix: "v".to_string(),
+            tag_style: TagStyle::Annotated,
+            pre_id: None,
+            prerelease_channels: vec!["alpha".to_string(), "beta".to_string(), "rc".to_string()],
+            sync_dependents: false,
+            extra_tags: vec![],
+            stage_extra_files: vec![],
+            verify_command: None,
+            registry: None,
+            prompt_options: PromptOption::ALL.to_vec(),
+            no_verify: false,
+            changelog_file: None,
+            edit_changelog: false,
+            publish: false,
+            profile: HashMap::new(),
+            branches: vec![],
+            snapshot_template: "{version}-dev.{date}+{sha}".to_string(),
+            gitlab: None,
         }
     }
 }